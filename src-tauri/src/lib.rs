@@ -3,6 +3,7 @@ mod storage;
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   tauri::Builder::default()
+    .manage(storage::StorageManager::new())
     .setup(|app| {
       if cfg!(debug_assertions) {
         app.handle().plugin(
@@ -18,7 +19,13 @@ pub fn run() {
       storage::load_lottery_data,
       storage::backup_data,
       storage::restore_from_backup,
-      storage::validate_data
+      storage::validate_data,
+      storage::draw_lottery,
+      storage::verify_cycle,
+      storage::list_backups,
+      storage::prune_backups,
+      storage::migrate_to_sqlite,
+      storage::lottery_statistics
     ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
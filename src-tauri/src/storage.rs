@@ -1,12 +1,43 @@
+use hmac::{Hmac, Mac};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use tokio::fs as tokio_fs;
+use tokio::io::AsyncWriteExt;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/**
+ * 存储层并发协调器
+ *
+ * 通过 `tauri::Builder::manage` 托管，内部持有一把互斥锁。所有触碰数据文件的命令
+ * 在读写前先获取该锁，确保自动保存与手动保存、备份等操作不会交叉写入或复制到半成品文件。
+ */
+pub struct StorageManager {
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl StorageManager {
+    pub fn new() -> Self {
+        Self {
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+impl Default for StorageManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /**
  * 奖品颜色枚举 - 与前端保持一致
  */
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum PrizeColor {
     Red,
@@ -24,6 +55,9 @@ pub struct Prize {
     pub name: String,
     pub description: Option<String>,
     pub icon: Option<String>,
+    /// 中奖权重，用于区分「大奖」与「好礼」等稀有度；为 `None` 时按 1 计
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
 }
 
 /**
@@ -64,6 +98,26 @@ pub struct LotteryCycle {
     pub completed: bool,
     #[serde(rename = "remainingDraws")]
     pub remaining_draws: RemainingDraws,
+    /// 服务器种子承诺值 commitment = SHA256(seed)，周期创建时公布，任何人都可核对
+    #[serde(rename = "commitment")]
+    pub commitment: Option<String>,
+    /// 服务器种子（十六进制），周期进行中对前端保持不透明，仅用于后端推导
+    #[serde(rename = "serverSeed", skip_serializing_if = "Option::is_none")]
+    pub server_seed: Option<String>,
+    /// 周期完成后揭示的种子，用户可据此重算每一次抽奖并与 commitment 对账
+    #[serde(rename = "revealedSeed", skip_serializing_if = "Option::is_none")]
+    pub revealed_seed: Option<String>,
+}
+
+/**
+ * 存储后端类型
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Json,
+    Sqlite,
 }
 
 /**
@@ -79,6 +133,12 @@ pub struct LotteryConfig {
     pub enable_animations: bool,
     #[serde(rename = "animationDuration")]
     pub animation_duration: u32,
+    /// 是否在每次保存前自动创建一份备份并清理旧备份（仅 JSON 后端；SQLite 后端保存时不备份）
+    #[serde(rename = "autoBackup", default)]
+    pub auto_backup: bool,
+    /// 存储后端：JSON 单文件或 SQLite 数据库
+    #[serde(rename = "storageBackend", default)]
+    pub storage_backend: StorageBackend,
 }
 
 /**
@@ -94,91 +154,308 @@ pub struct LotteryState {
     pub config: LotteryConfig,
 }
 
+/**
+ * 备份文件的摘要信息，供 UI 列举与管理备份
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub path: String,
+    /// 文件名中内嵌的时间戳，形如 `20260725_101530`
+    pub timestamp: String,
+    pub size: u64,
+    /// 内容是否能解析并通过完整性校验
+    pub valid: bool,
+}
+
+/// 自动备份默认保留的份数
+const DEFAULT_BACKUP_KEEP: usize = 10;
+
 /**
  * 获取数据存储路径
  */
 fn get_data_path() -> Result<PathBuf, String> {
+    Ok(get_lottery_dir()?.join("data.json"))
+}
+
+/**
+ * 获取 lottery-game 数据目录，必要时创建
+ */
+fn get_lottery_dir() -> Result<PathBuf, String> {
     let documents_dir = dirs::document_dir()
         .ok_or("无法获取用户文档目录")?;
-    
+
     let lottery_dir = documents_dir.join("lottery-game");
-    
+
     // 确保目录存在
     if !lottery_dir.exists() {
         fs::create_dir_all(&lottery_dir)
             .map_err(|e| format!("创建目录失败: {}", e))?;
     }
-    
-    Ok(lottery_dir.join("data.json"))
+
+    Ok(lottery_dir)
 }
 
 /**
  * 获取备份路径
  */
 fn get_backup_path() -> Result<PathBuf, String> {
-    let documents_dir = dirs::document_dir()
-        .ok_or("无法获取用户文档目录")?;
-    
-    let lottery_dir = documents_dir.join("lottery-game");
     let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-    
-    Ok(lottery_dir.join(format!("data_backup_{}.json", timestamp)))
+    Ok(get_lottery_dir()?.join(format!("data_backup_{}.json", timestamp)))
 }
 
 /**
  * 保存抽奖数据
  */
 #[tauri::command]
-pub async fn save_lottery_data(data: LotteryState) -> Result<(), String> {
+pub async fn save_lottery_data(
+    manager: tauri::State<'_, StorageManager>,
+    data: LotteryState,
+) -> Result<(), String> {
+    let _guard = manager.lock.lock().await;
+
+    // 前端传回的状态已被抹去 server_seed，落盘前按 cycle_id 从持久化存储找回，避免覆写丢失
+    let mut data = data;
+    restore_server_seeds(&mut data).await?;
+
+    // 与 load 共用同一后端判定：配置请求 SQLite 或数据库已存在（已迁移）即走 SQLite
+    if use_sqlite_backend(&data.config)? {
+        return save_to_sqlite(&data);
+    }
+
     let data_path = get_data_path()?;
-    
+
+    // 覆盖前按需自动快照，并清理多余备份
+    if data.config.auto_backup && data_path.exists() {
+        let backup_path = get_backup_path()?;
+        tokio_fs::copy(&data_path, &backup_path)
+            .await
+            .map_err(|e| format!("自动备份失败: {}", e))?;
+        log::info!("保存前已自动备份到: {:?}", backup_path);
+        prune_backups_internal(DEFAULT_BACKUP_KEEP).await?;
+    }
+
     // 序列化数据
     let json_data = serde_json::to_string_pretty(&data)
         .map_err(|e| format!("数据序列化失败: {}", e))?;
-    
-    // 异步写入文件
-    tokio_fs::write(&data_path, json_data)
+
+    // 原子写入：先写临时文件并 fsync，再 rename 覆盖，避免写入中途崩溃导致文件截断
+    let tmp_path = data_path.with_file_name("data.json.tmp");
+    let mut file = tokio_fs::File::create(&tmp_path)
         .await
-        .map_err(|e| format!("写入文件失败: {}", e))?;
-    
+        .map_err(|e| format!("创建临时文件失败: {}", e))?;
+    file.write_all(json_data.as_bytes())
+        .await
+        .map_err(|e| format!("写入临时文件失败: {}", e))?;
+    file.sync_all()
+        .await
+        .map_err(|e| format!("刷新临时文件失败: {}", e))?;
+    drop(file);
+
+    tokio_fs::rename(&tmp_path, &data_path)
+        .await
+        .map_err(|e| format!("替换数据文件失败: {}", e))?;
+
     log::info!("抽奖数据已保存到: {:?}", data_path);
     Ok(())
 }
 
 /**
  * 加载抽奖数据
+ *
+ * 返回给前端前会抹去所有周期的 `server_seed`：种子属于后端的承诺—揭示秘密，
+ * 周期进行中绝不下发，否则前端可据其提前推算每一次 `prize_id`，击穿 commitment 的意义。
+ * 前端只拿到 `commitment`，周期完成后再通过 `revealed_seed` 对账。
  */
 #[tauri::command]
-pub async fn load_lottery_data() -> Result<LotteryState, String> {
+pub async fn load_lottery_data(
+    manager: tauri::State<'_, StorageManager>,
+) -> Result<LotteryState, String> {
+    let _guard = manager.lock.lock().await;
+
+    // 首次运行时把默认状态连同种子落盘，保证后端后续能取回同一颗种子
+    let existed = store_exists()?;
+    let mut state = read_full_state().await?;
+    if !existed {
+        log::info!("数据文件不存在，已初始化并持久化默认状态");
+        persist_state_internal(&state).await?;
+    }
+
+    withhold_server_seeds(&mut state);
+    Ok(state)
+}
+
+/**
+ * 读取持久化的完整状态（含 `server_seed`），不加锁、不做下发前处理
+ *
+ * 既供 `load_lottery_data` 下发前抹除种子，也供 `draw_lottery`/`save_lottery_data`
+ * 在后端内部取回种子，确保种子只在后端流转。
+ */
+async fn read_full_state() -> Result<LotteryState, String> {
+    // 与 save 共用同一后端判定，避免两条路径各自为政导致写入后读不到
+    if use_sqlite_backend(&load_json_config()?)? {
+        return load_from_sqlite();
+    }
+
     let data_path = get_data_path()?;
-    
+
     // 检查文件是否存在
     if !data_path.exists() {
-        log::info!("数据文件不存在，返回默认状态");
         return Ok(create_default_state());
     }
-    
+
     // 异步读取文件
     let json_data = tokio_fs::read_to_string(&data_path)
         .await
         .map_err(|e| format!("读取文件失败: {}", e))?;
-    
-    // 反序列化数据
-    let lottery_state: LotteryState = serde_json::from_str(&json_data)
-        .map_err(|e| {
+
+    // 反序列化数据；若失败则尝试从备份自愈
+    let lottery_state: LotteryState = match serde_json::from_str(&json_data) {
+        Ok(state) => state,
+        Err(e) => {
             log::error!("数据反序列化失败: {}", e);
-            format!("数据格式错误，可能已损坏: {}", e)
-        })?;
-    
+            match recover_from_backups().await? {
+                Some(state) => {
+                    // 将可用备份回写为主数据文件
+                    let restored = serde_json::to_string_pretty(&state)
+                        .map_err(|e| format!("数据序列化失败: {}", e))?;
+                    tokio_fs::write(&data_path, restored)
+                        .await
+                        .map_err(|e| format!("写入文件失败: {}", e))?;
+                    log::warn!("数据文件已损坏，已从最新可用备份自动恢复");
+                    return Ok(state);
+                }
+                None => return Err(format!("数据格式错误，可能已损坏: {}", e)),
+            }
+        }
+    };
+
     log::info!("抽奖数据已加载从: {:?}", data_path);
     Ok(lottery_state)
 }
 
+/**
+ * 判断是否已有任一后端的持久化存储
+ */
+fn store_exists() -> Result<bool, String> {
+    Ok(get_db_path()?.exists() || get_data_path()?.exists())
+}
+
+/**
+ * 抹去状态中所有周期的 `server_seed`，供下发前端前调用
+ */
+fn withhold_server_seeds(state: &mut LotteryState) {
+    state.current_cycle.server_seed = None;
+    for cycle in &mut state.history {
+        cycle.server_seed = None;
+    }
+}
+
+/**
+ * 用持久化存储中的 `server_seed` 回填状态里缺失的种子（按 `cycle_id` 匹配）
+ *
+ * 前端持有的状态已被抹去种子，保存时据此找回，避免把种子覆写为空而丢失。
+ */
+async fn restore_server_seeds(data: &mut LotteryState) -> Result<(), String> {
+    if !store_exists()? {
+        return Ok(());
+    }
+    let persisted = read_full_state().await?;
+    let mut seeds: HashMap<String, String> = HashMap::new();
+    if let Some(seed) = persisted.current_cycle.server_seed {
+        seeds.insert(persisted.current_cycle.id, seed);
+    }
+    for cycle in persisted.history {
+        if let Some(seed) = cycle.server_seed {
+            seeds.insert(cycle.id, seed);
+        }
+    }
+
+    if data.current_cycle.server_seed.is_none() {
+        data.current_cycle.server_seed = seeds.get(&data.current_cycle.id).cloned();
+    }
+    for cycle in &mut data.history {
+        if cycle.server_seed.is_none() {
+            cycle.server_seed = seeds.get(&cycle.id).cloned();
+        }
+    }
+    Ok(())
+}
+
+/**
+ * 依当前后端把完整状态写入持久化存储（不加锁、不自动备份），供初始化落盘复用
+ */
+async fn persist_state_internal(data: &LotteryState) -> Result<(), String> {
+    if use_sqlite_backend(&data.config)? {
+        return save_to_sqlite(data);
+    }
+    let data_path = get_data_path()?;
+    let json_data = serde_json::to_string_pretty(data)
+        .map_err(|e| format!("数据序列化失败: {}", e))?;
+    let tmp_path = data_path.with_file_name("data.json.tmp");
+    let mut file = tokio_fs::File::create(&tmp_path)
+        .await
+        .map_err(|e| format!("创建临时文件失败: {}", e))?;
+    file.write_all(json_data.as_bytes())
+        .await
+        .map_err(|e| format!("写入临时文件失败: {}", e))?;
+    file.sync_all()
+        .await
+        .map_err(|e| format!("刷新临时文件失败: {}", e))?;
+    drop(file);
+    tokio_fs::rename(&tmp_path, &data_path)
+        .await
+        .map_err(|e| format!("替换数据文件失败: {}", e))?;
+    Ok(())
+}
+
+/**
+ * 扫描数据目录内的 `data_backup_*.json`，返回最新一个能解析且通过校验的状态
+ */
+async fn recover_from_backups() -> Result<Option<LotteryState>, String> {
+    let lottery_dir = get_lottery_dir()?;
+
+    // 收集候选备份并按文件名（含时间戳）倒序排列，最新优先
+    let mut backups: Vec<PathBuf> = Vec::new();
+    let mut entries = tokio_fs::read_dir(&lottery_dir)
+        .await
+        .map_err(|e| format!("读取目录失败: {}", e))?;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("遍历目录失败: {}", e))?
+    {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with("data_backup_") && name.ends_with(".json") {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+    backups.reverse();
+
+    for path in backups {
+        let Ok(content) = tokio_fs::read_to_string(&path).await else {
+            continue;
+        };
+        let Ok(state) = serde_json::from_str::<LotteryState>(&content) else {
+            continue;
+        };
+        if validate_lottery_state(&state) == Ok(true) {
+            log::warn!("选用备份进行恢复: {:?}", path);
+            return Ok(Some(state));
+        }
+    }
+
+    Ok(None)
+}
+
 /**
  * 备份数据
  */
 #[tauri::command]
-pub async fn backup_data() -> Result<String, String> {
+pub async fn backup_data(
+    manager: tauri::State<'_, StorageManager>,
+) -> Result<String, String> {
+    let _guard = manager.lock.lock().await;
     let data_path = get_data_path()?;
     let backup_path = get_backup_path()?;
     
@@ -202,7 +479,11 @@ pub async fn backup_data() -> Result<String, String> {
  * 从备份恢复数据
  */
 #[tauri::command]
-pub async fn restore_from_backup(backup_path: String) -> Result<(), String> {
+pub async fn restore_from_backup(
+    manager: tauri::State<'_, StorageManager>,
+    backup_path: String,
+) -> Result<(), String> {
+    let _guard = manager.lock.lock().await;
     let backup_path = PathBuf::from(backup_path);
     let data_path = get_data_path()?;
     
@@ -228,11 +509,112 @@ pub async fn restore_from_backup(backup_path: String) -> Result<(), String> {
     Ok(())
 }
 
+/**
+ * 列出全部备份
+ *
+ * 枚举 `data_backup_*.json`，解析文件名中的时间戳、文件大小，并标记其是否能解析且通过校验，
+ * 按时间戳由新到旧排序。
+ */
+#[tauri::command]
+pub async fn list_backups(
+    manager: tauri::State<'_, StorageManager>,
+) -> Result<Vec<BackupInfo>, String> {
+    let _guard = manager.lock.lock().await;
+    list_backups_internal().await
+}
+
+/**
+ * 清理备份，仅保留最新的 `keep` 个有效备份
+ */
+#[tauri::command]
+pub async fn prune_backups(
+    manager: tauri::State<'_, StorageManager>,
+    keep: usize,
+) -> Result<(), String> {
+    let _guard = manager.lock.lock().await;
+    prune_backups_internal(keep).await
+}
+
+/**
+ * 枚举并解析全部备份（不获取锁，供持锁调用方复用）
+ */
+async fn list_backups_internal() -> Result<Vec<BackupInfo>, String> {
+    let lottery_dir = get_lottery_dir()?;
+
+    let mut backups: Vec<BackupInfo> = Vec::new();
+    let mut entries = tokio_fs::read_dir(&lottery_dir)
+        .await
+        .map_err(|e| format!("读取目录失败: {}", e))?;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("遍历目录失败: {}", e))?
+    {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !(name.starts_with("data_backup_") && name.ends_with(".json")) {
+            continue;
+        }
+
+        let timestamp = name
+            .trim_start_matches("data_backup_")
+            .trim_end_matches(".json")
+            .to_string();
+        let size = entry.metadata().await.map(|m| m.len()).unwrap_or(0);
+        let valid = match tokio_fs::read_to_string(entry.path()).await {
+            Ok(content) => match serde_json::from_str::<LotteryState>(&content) {
+                Ok(state) => validate_lottery_state(&state) == Ok(true),
+                Err(_) => false,
+            },
+            Err(_) => false,
+        };
+
+        backups.push(BackupInfo {
+            path: entry.path().to_string_lossy().to_string(),
+            timestamp,
+            size,
+            valid,
+        });
+    }
+
+    // 时间戳格式可直接按字典序倒排，最新优先
+    backups.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(backups)
+}
+
+/**
+ * 清理备份逻辑（不获取锁，供持锁调用方复用）
+ */
+async fn prune_backups_internal(keep: usize) -> Result<(), String> {
+    let backups = list_backups_internal().await?;
+
+    let mut kept = 0usize;
+    for backup in backups {
+        // 无效备份不自动清理：它可能是用户留作手动排查或恢复的样本
+        if !backup.valid {
+            continue;
+        }
+        if kept < keep {
+            kept += 1;
+            continue;
+        }
+        // 仅删除超出保留额度的有效备份
+        tokio_fs::remove_file(&backup.path)
+            .await
+            .map_err(|e| format!("删除备份失败: {}", e))?;
+        log::info!("已清理备份: {}", backup.path);
+    }
+
+    Ok(())
+}
+
 /**
  * 验证数据完整性
  */
 #[tauri::command]
-pub async fn validate_data() -> Result<bool, String> {
+pub async fn validate_data(
+    manager: tauri::State<'_, StorageManager>,
+) -> Result<bool, String> {
+    let _guard = manager.lock.lock().await;
     let data_path = get_data_path()?;
     
     // 如果文件不存在，认为是有效的（将创建默认状态）
@@ -260,7 +642,8 @@ pub async fn validate_data() -> Result<bool, String> {
  */
 fn create_default_state() -> LotteryState {
     let now = chrono::Utc::now().timestamp_millis();
-    
+    let (server_seed, commitment) = generate_seed_commitment();
+
     LotteryState {
         current_cycle: LotteryCycle {
             id: format!("cycle_{}_{}", now, uuid::Uuid::new_v4().simple()),
@@ -273,6 +656,9 @@ fn create_default_state() -> LotteryState {
                 yellow: 2,
                 blue: 2,
             },
+            commitment: Some(commitment),
+            server_seed: Some(server_seed),
+            revealed_seed: None,
         },
         history: Vec::new(),
         available_prizes: create_default_prizes(),
@@ -281,6 +667,8 @@ fn create_default_state() -> LotteryState {
             draws_per_color: 2,
             enable_animations: true,
             animation_duration: 2000,
+            auto_backup: true,
+            storage_backend: StorageBackend::Json,
         },
     }
 }
@@ -296,6 +684,7 @@ fn create_default_prizes() -> Vec<Prize> {
             name: "红色大奖".to_string(),
             description: Some("价值丰厚的红色奖品".to_string()),
             icon: None,
+            weight: None,
         },
         Prize {
             id: "prize_red_2".to_string(),
@@ -303,6 +692,7 @@ fn create_default_prizes() -> Vec<Prize> {
             name: "红色好礼".to_string(),
             description: Some("精美的红色礼品".to_string()),
             icon: None,
+            weight: None,
         },
         Prize {
             id: "prize_yellow_1".to_string(),
@@ -310,6 +700,7 @@ fn create_default_prizes() -> Vec<Prize> {
             name: "黄色大奖".to_string(),
             description: Some("价值丰厚的黄色奖品".to_string()),
             icon: None,
+            weight: None,
         },
         Prize {
             id: "prize_yellow_2".to_string(),
@@ -317,6 +708,7 @@ fn create_default_prizes() -> Vec<Prize> {
             name: "黄色好礼".to_string(),
             description: Some("精美的黄色礼品".to_string()),
             icon: None,
+            weight: None,
         },
         Prize {
             id: "prize_blue_1".to_string(),
@@ -324,6 +716,7 @@ fn create_default_prizes() -> Vec<Prize> {
             name: "蓝色大奖".to_string(),
             description: Some("价值丰厚的蓝色奖品".to_string()),
             icon: None,
+            weight: None,
         },
         Prize {
             id: "prize_blue_2".to_string(),
@@ -331,6 +724,7 @@ fn create_default_prizes() -> Vec<Prize> {
             name: "蓝色好礼".to_string(),
             description: Some("精美的蓝色礼品".to_string()),
             icon: None,
+            weight: None,
         },
     ]
 }
@@ -361,6 +755,1032 @@ fn validate_lottery_state(state: &LotteryState) -> Result<bool, String> {
     if state.available_prizes.is_empty() {
         return Ok(false);
     }
-    
+
+    // 每个颜色奖品池的总权重不得为 0，否则该颜色无法抽出奖品
+    for color in [PrizeColor::Red, PrizeColor::Yellow, PrizeColor::Blue] {
+        let total: u64 = state
+            .available_prizes
+            .iter()
+            .filter(|p| p.color == color)
+            .map(|p| prize_weight(p) as u64)
+            .sum();
+        let has_pool = state.available_prizes.iter().any(|p| p.color == color);
+        if has_pool && total == 0 {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/**
+ * 生成随机服务器种子及其承诺值
+ *
+ * 返回 `(seed_hex, commitment_hex)`，其中 `commitment = SHA256(seed)`。
+ */
+fn generate_seed_commitment() -> (String, String) {
+    let mut seed = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut seed);
+    let commitment = Sha256::digest(seed);
+    (to_hex(&seed), to_hex(&commitment))
+}
+
+/**
+ * 推导某次抽奖的随机摘要
+ *
+ * `digest = HMAC-SHA256(seed, cycle_id || n_le_bytes)`，调用方取其前若干字节作为随机数。
+ */
+fn draw_digest(seed: &[u8], cycle_id: &str, n: u32) -> Result<[u8; 32], String> {
+    let mut mac = HmacSha256::new_from_slice(seed)
+        .map_err(|e| format!("初始化 HMAC 失败: {}", e))?;
+    mac.update(cycle_id.as_bytes());
+    mac.update(&n.to_le_bytes());
+    Ok(mac.finalize().into_bytes().into())
+}
+
+/**
+ * 按固定顺序（红、黄、蓝）列出仍有剩余次数的颜色
+ */
+fn eligible_colors(remaining: &RemainingDraws) -> Vec<PrizeColor> {
+    let mut colors = Vec::new();
+    if remaining.red > 0 {
+        colors.push(PrizeColor::Red);
+    }
+    if remaining.yellow > 0 {
+        colors.push(PrizeColor::Yellow);
+    }
+    if remaining.blue > 0 {
+        colors.push(PrizeColor::Blue);
+    }
+    colors
+}
+
+/**
+ * 扣减指定颜色的剩余抽奖次数
+ */
+fn decrement_color(remaining: &mut RemainingDraws, color: &PrizeColor) {
+    match color {
+        PrizeColor::Red => remaining.red = remaining.red.saturating_sub(1),
+        PrizeColor::Yellow => remaining.yellow = remaining.yellow.saturating_sub(1),
+        PrizeColor::Blue => remaining.blue = remaining.blue.saturating_sub(1),
+    }
+}
+
+/**
+ * 在奖品池中按 id 查出颜色，奖品池是颜色的唯一权威来源
+ */
+fn color_of(prizes: &[Prize], prize_id: &str) -> Option<PrizeColor> {
+    prizes
+        .iter()
+        .find(|p| p.id == prize_id)
+        .map(|p| p.color.clone())
+}
+
+/**
+ * 字节切片转小写十六进制字符串
+ */
+fn to_hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
+
+/**
+ * 十六进制字符串转字节，格式非法时报错
+ */
+fn from_hex(s: &str) -> Result<Vec<u8>, String> {
+    if s.len() % 2 != 0 {
+        return Err("十六进制长度非法".to_string());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|e| format!("十六进制解析失败: {}", e)))
+        .collect()
+}
+
+/**
+ * 奖品权重，未设置时按 1 计
+ */
+fn prize_weight(prize: &Prize) -> u32 {
+    prize.weight.unwrap_or(1)
+}
+
+/**
+ * 按权重从候选奖品中选出一个的下标
+ *
+ * 构造累积权重数组 `cum[i] = cum[i-1] + weight[i]`，在 `[0, total_weight)` 内取随机数
+ * `r`，二分查找首个满足 `cum[i] > r` 的下标。总权重为 0 时返回 `None`。
+ */
+fn select_weighted(prizes: &[&Prize], rand: u64) -> Option<usize> {
+    let mut cum = Vec::with_capacity(prizes.len());
+    let mut total: u64 = 0;
+    for p in prizes {
+        total += prize_weight(p) as u64;
+        cum.push(total);
+    }
+    if total == 0 {
+        return None;
+    }
+    let r = rand % total;
+    // partition_point 返回首个使 `cum[i] <= r` 不成立的下标，即首个 `cum[i] > r`
+    Some(cum.partition_point(|&c| c <= r))
+}
+
+/**
+ * 在 Rust 侧执行一次可验证的抽奖（commit–reveal 随机性）
+ *
+ * 服务器种子只在后端流转：前端传回的状态已被抹去 `server_seed`，这里按 `cycle_id`
+ * 从持久化存储取回真正的种子再推导，避免前端据种子提前推算后续结果。返回给前端的
+ * 状态同样不含 `server_seed`，周期完成后才揭示 `revealed_seed` 供对账。
+ */
+#[tauri::command]
+pub async fn draw_lottery(
+    manager: tauri::State<'_, StorageManager>,
+    state: LotteryState,
+) -> Result<(LotteryResult, LotteryState), String> {
+    let _guard = manager.lock.lock().await;
+
+    if state.current_cycle.completed {
+        return Err("当前周期已完成，无法继续抽奖".to_string());
+    }
+
+    // 种子不信任前端副本，一律以持久化存储中的为准
+    let persisted = read_full_state().await?;
+    let seed_hex = if persisted.current_cycle.id == state.current_cycle.id {
+        persisted.current_cycle.server_seed.clone()
+    } else {
+        None
+    }
+    .ok_or("当前周期缺少服务器种子，无法抽奖")?;
+
+    let (result, mut next) = perform_draw(state, &seed_hex)?;
+    withhold_server_seeds(&mut next);
+    Ok((result, next))
+}
+
+/**
+ * 以给定种子执行一次抽奖的纯逻辑（不触碰存储），供命令层与测试复用
+ *
+ * 基于种子推导随机数，在仍可抽取的颜色与其奖品池内选出结果，扣减对应颜色的剩余次数
+ * 并追加记录。当周期所有次数用尽时，标记完成并揭示种子，任何人即可用 `verify_cycle`
+ * 重算并与 `commitment` 对账。返回状态不写入 `server_seed`。
+ */
+fn perform_draw(
+    mut state: LotteryState,
+    seed_hex: &str,
+) -> Result<(LotteryResult, LotteryState), String> {
+    let now = chrono::Utc::now().timestamp_millis();
+
+    if state.current_cycle.completed {
+        return Err("当前周期已完成，无法继续抽奖".to_string());
+    }
+
+    let seed = from_hex(seed_hex)?;
+    let cycle_id = state.current_cycle.id.clone();
+    let n = state.current_cycle.results.len() as u32;
+
+    let digest = draw_digest(&seed, &cycle_id, n)?;
+
+    // 在仍有剩余次数的颜色中按随机数取模选择
+    let eligible = eligible_colors(&state.current_cycle.remaining_draws);
+    if eligible.is_empty() {
+        return Err("没有可抽取的颜色".to_string());
+    }
+    let color_rand = u64::from_be_bytes(digest[0..8].try_into().unwrap());
+    let color = eligible[(color_rand % eligible.len() as u64) as usize].clone();
+
+    // 在选中颜色的奖品池内挑选具体奖品
+    let prizes: Vec<&Prize> = state
+        .available_prizes
+        .iter()
+        .filter(|p| p.color == color)
+        .collect();
+    if prizes.is_empty() {
+        return Err("选中颜色没有可用奖品".to_string());
+    }
+    let prize_rand = u64::from_be_bytes(digest[8..16].try_into().unwrap());
+    let prize_idx = select_weighted(&prizes, prize_rand).ok_or("选中颜色的奖品总权重为 0")?;
+    let prize_id = prizes[prize_idx].id.clone();
+
+    decrement_color(&mut state.current_cycle.remaining_draws, &color);
+
+    let result = LotteryResult {
+        prize_id,
+        timestamp: now,
+        cycle_id,
+        draw_number: n,
+    };
+    state.current_cycle.results.push(result.clone());
+
+    // 次数用尽即完成周期并揭示种子
+    let rd = &state.current_cycle.remaining_draws;
+    if rd.red + rd.yellow + rd.blue == 0 {
+        state.current_cycle.completed = true;
+        state.current_cycle.end_time = Some(now);
+        state.current_cycle.revealed_seed = Some(seed_hex.to_string());
+    }
+
+    Ok((result, state))
+}
+
+/**
+ * 校验一个已完成周期的抽奖是否可复现
+ *
+ * 要求周期已揭示种子：先核对 `SHA256(seed) == commitment`，再连同当时的奖品池按原始
+ * 推导逐次重放，确认每条记录的颜色、池内加权选中的 `prize_id` 与 `draw_number` 均与
+ * 种子一致。奖品颜色一律取自传入的奖品池而非 id 字面。任一环节不符返回 `false`。
+ */
+#[tauri::command]
+pub async fn verify_cycle(cycle: LotteryCycle, prizes: Vec<Prize>) -> Result<bool, String> {
+    let seed_hex = match &cycle.revealed_seed {
+        Some(s) => s.clone(),
+        None => return Ok(false),
+    };
+    let seed = from_hex(&seed_hex)?;
+
+    // 承诺值必须匹配
+    match &cycle.commitment {
+        Some(c) if *c == to_hex(&Sha256::digest(&seed)) => {}
+        _ => return Ok(false),
+    }
+
+    // 由最终剩余次数加上各颜色已抽次数，反推周期起始时的剩余次数
+    let mut remaining = cycle.remaining_draws.clone();
+    for r in &cycle.results {
+        match color_of(&prizes, &r.prize_id) {
+            Some(PrizeColor::Red) => remaining.red += 1,
+            Some(PrizeColor::Yellow) => remaining.yellow += 1,
+            Some(PrizeColor::Blue) => remaining.blue += 1,
+            None => return Ok(false),
+        }
+    }
+
+    // 逐次重放并与记录比对
+    for (n, r) in cycle.results.iter().enumerate() {
+        let n = n as u32;
+        if r.draw_number != n {
+            return Ok(false);
+        }
+        let digest = draw_digest(&seed, &cycle.id, n)?;
+        let eligible = eligible_colors(&remaining);
+        if eligible.is_empty() {
+            return Ok(false);
+        }
+        let color_rand = u64::from_be_bytes(digest[0..8].try_into().unwrap());
+        let color = eligible[(color_rand % eligible.len() as u64) as usize].clone();
+
+        if Some(&color) != color_of(&prizes, &r.prize_id).as_ref() {
+            return Ok(false);
+        }
+
+        // 重算色池内的加权选择，确认 prize_id 与种子推导一致
+        let pool: Vec<&Prize> = prizes.iter().filter(|p| p.color == color).collect();
+        let prize_rand = u64::from_be_bytes(digest[8..16].try_into().unwrap());
+        let prize_idx = match select_weighted(&pool, prize_rand) {
+            Some(idx) => idx,
+            None => return Ok(false),
+        };
+        if pool[prize_idx].id != r.prize_id {
+            return Ok(false);
+        }
+
+        decrement_color(&mut remaining, &color);
+    }
+
     Ok(true)
-}
\ No newline at end of file
+}
+/**
+ * 获取 SQLite 数据库文件路径
+ */
+fn get_db_path() -> Result<PathBuf, String> {
+    Ok(get_lottery_dir()?.join("data.db"))
+}
+
+/**
+ * 判定是否应使用 SQLite 后端
+ *
+ * save 与 load 共用此判定，保证同一份配置指向同一个后端：配置显式请求 SQLite，
+ * 或数据库文件已存在（迁移后不可逆），都以 SQLite 为准。
+ */
+fn use_sqlite_backend(config: &LotteryConfig) -> Result<bool, String> {
+    Ok(config.storage_backend == StorageBackend::Sqlite || get_db_path()?.exists())
+}
+
+/**
+ * 读取 JSON 存储中的配置，供 load 在选择后端前参考；文件缺失或损坏时退回默认配置
+ */
+fn load_json_config() -> Result<LotteryConfig, String> {
+    let data_path = get_data_path()?;
+    if !data_path.exists() {
+        return Ok(create_default_state().config);
+    }
+    match fs::read_to_string(&data_path) {
+        Ok(content) => match serde_json::from_str::<LotteryState>(&content) {
+            Ok(state) => Ok(state.config),
+            Err(_) => Ok(create_default_state().config),
+        },
+        Err(_) => Ok(create_default_state().config),
+    }
+}
+
+/**
+ * 颜色到数据库字符串
+ */
+fn color_to_db(color: &PrizeColor) -> &'static str {
+    match color {
+        PrizeColor::Red => "red",
+        PrizeColor::Yellow => "yellow",
+        PrizeColor::Blue => "blue",
+    }
+}
+
+/**
+ * 数据库字符串到颜色
+ */
+fn color_from_db(s: &str) -> Result<PrizeColor, String> {
+    match s {
+        "red" => Ok(PrizeColor::Red),
+        "yellow" => Ok(PrizeColor::Yellow),
+        "blue" => Ok(PrizeColor::Blue),
+        other => Err(format!("未知颜色: {}", other)),
+    }
+}
+
+/**
+ * 打开数据库连接并确保表结构存在
+ *
+ * 采用 `cycles` / `results` / `prizes` 三张表加一张单行 `config`；`results` 以
+ * `(cycle_id, draw_number)` 唯一，便于以增量插入写入每条新结果而非整篇重写。
+ */
+fn open_db() -> Result<rusqlite::Connection, String> {
+    let conn = rusqlite::Connection::open(get_db_path()?)
+        .map_err(|e| format!("打开数据库失败: {}", e))?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS cycles (
+            id TEXT PRIMARY KEY,
+            start_time INTEGER NOT NULL,
+            end_time INTEGER,
+            completed INTEGER NOT NULL,
+            remaining_red INTEGER NOT NULL,
+            remaining_yellow INTEGER NOT NULL,
+            remaining_blue INTEGER NOT NULL,
+            commitment TEXT,
+            server_seed TEXT,
+            revealed_seed TEXT,
+            is_current INTEGER NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS results (
+            cycle_id TEXT NOT NULL,
+            prize_id TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            draw_number INTEGER NOT NULL,
+            PRIMARY KEY (cycle_id, draw_number)
+         );
+         CREATE TABLE IF NOT EXISTS prizes (
+            id TEXT PRIMARY KEY,
+            color TEXT NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            icon TEXT,
+            weight INTEGER
+         );
+         CREATE TABLE IF NOT EXISTS config (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            draws_per_cycle INTEGER NOT NULL,
+            draws_per_color INTEGER NOT NULL,
+            enable_animations INTEGER NOT NULL,
+            animation_duration INTEGER NOT NULL,
+            auto_backup INTEGER NOT NULL,
+            storage_backend TEXT NOT NULL
+         );",
+    )
+    .map_err(|e| format!("初始化数据库结构失败: {}", e))?;
+    Ok(conn)
+}
+
+/**
+ * 将一个周期及其结果写入数据库（结果以增量方式插入）
+ */
+fn upsert_cycle(conn: &rusqlite::Connection, cycle: &LotteryCycle, is_current: bool) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO cycles (id, start_time, end_time, completed, remaining_red,
+            remaining_yellow, remaining_blue, commitment, server_seed, revealed_seed, is_current)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+         ON CONFLICT(id) DO UPDATE SET
+            end_time = excluded.end_time,
+            completed = excluded.completed,
+            remaining_red = excluded.remaining_red,
+            remaining_yellow = excluded.remaining_yellow,
+            remaining_blue = excluded.remaining_blue,
+            revealed_seed = excluded.revealed_seed,
+            is_current = excluded.is_current",
+        rusqlite::params![
+            cycle.id,
+            cycle.start_time,
+            cycle.end_time,
+            cycle.completed as i64,
+            cycle.remaining_draws.red,
+            cycle.remaining_draws.yellow,
+            cycle.remaining_draws.blue,
+            cycle.commitment,
+            cycle.server_seed,
+            cycle.revealed_seed,
+            is_current as i64,
+        ],
+    )
+    .map_err(|e| format!("写入周期失败: {}", e))?;
+
+    for r in &cycle.results {
+        conn.execute(
+            "INSERT OR IGNORE INTO results (cycle_id, prize_id, timestamp, draw_number)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![r.cycle_id, r.prize_id, r.timestamp, r.draw_number],
+        )
+        .map_err(|e| format!("写入抽奖结果失败: {}", e))?;
+    }
+    Ok(())
+}
+
+/**
+ * 以 SQLite 后端保存整体状态
+ */
+fn save_to_sqlite(data: &LotteryState) -> Result<(), String> {
+    let mut conn = open_db()?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开启事务失败: {}", e))?;
+
+    // 奖品与配置整体覆盖写入
+    tx.execute("DELETE FROM prizes", [])
+        .map_err(|e| format!("清理奖品失败: {}", e))?;
+    for p in &data.available_prizes {
+        tx.execute(
+            "INSERT INTO prizes (id, color, name, description, icon, weight)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                p.id,
+                color_to_db(&p.color),
+                p.name,
+                p.description,
+                p.icon,
+                p.weight,
+            ],
+        )
+        .map_err(|e| format!("写入奖品失败: {}", e))?;
+    }
+    tx.execute(
+        "INSERT INTO config (id, draws_per_cycle, draws_per_color, enable_animations,
+            animation_duration, auto_backup, storage_backend)
+         VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(id) DO UPDATE SET
+            draws_per_cycle = excluded.draws_per_cycle,
+            draws_per_color = excluded.draws_per_color,
+            enable_animations = excluded.enable_animations,
+            animation_duration = excluded.animation_duration,
+            auto_backup = excluded.auto_backup,
+            storage_backend = excluded.storage_backend",
+        rusqlite::params![
+            data.config.draws_per_cycle,
+            data.config.draws_per_color,
+            data.config.enable_animations as i64,
+            data.config.animation_duration,
+            data.config.auto_backup as i64,
+            color_backend_to_db(data.config.storage_backend),
+        ],
+    )
+    .map_err(|e| format!("写入配置失败: {}", e))?;
+
+    // 当前周期标记为 current，其余历史周期保留
+    tx.execute("UPDATE cycles SET is_current = 0", [])
+        .map_err(|e| format!("重置当前周期标记失败: {}", e))?;
+    for cycle in &data.history {
+        upsert_cycle(&tx, cycle, false)?;
+    }
+    upsert_cycle(&tx, &data.current_cycle, true)?;
+
+    tx.commit().map_err(|e| format!("提交事务失败: {}", e))?;
+    log::info!("抽奖数据已保存到 SQLite");
+    Ok(())
+}
+
+/**
+ * 存储后端到数据库字符串
+ */
+fn color_backend_to_db(backend: StorageBackend) -> &'static str {
+    match backend {
+        StorageBackend::Json => "json",
+        StorageBackend::Sqlite => "sqlite",
+    }
+}
+
+/**
+ * 从单条数据库记录重建一个周期
+ */
+fn load_cycle(conn: &rusqlite::Connection, id: &str) -> Result<LotteryCycle, String> {
+    let (start_time, end_time, completed, red, yellow, blue, commitment, server_seed, revealed_seed) =
+        conn.query_row(
+            "SELECT start_time, end_time, completed, remaining_red, remaining_yellow,
+                remaining_blue, commitment, server_seed, revealed_seed FROM cycles WHERE id = ?1",
+            rusqlite::params![id],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, u32>(3)?,
+                    row.get::<_, u32>(4)?,
+                    row.get::<_, u32>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                    row.get::<_, Option<String>>(7)?,
+                    row.get::<_, Option<String>>(8)?,
+                ))
+            },
+        )
+        .map_err(|e| format!("读取周期失败: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT prize_id, timestamp, draw_number FROM results WHERE cycle_id = ?1 ORDER BY draw_number")
+        .map_err(|e| format!("查询结果失败: {}", e))?;
+    let results = stmt
+        .query_map(rusqlite::params![id], |row| {
+            Ok(LotteryResult {
+                prize_id: row.get(0)?,
+                timestamp: row.get(1)?,
+                cycle_id: id.to_string(),
+                draw_number: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("读取结果失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取结果失败: {}", e))?;
+
+    Ok(LotteryCycle {
+        id: id.to_string(),
+        start_time,
+        end_time,
+        results,
+        completed: completed != 0,
+        remaining_draws: RemainingDraws { red, yellow, blue },
+        commitment,
+        server_seed,
+        revealed_seed,
+    })
+}
+
+/**
+ * 以 SQLite 后端加载整体状态
+ */
+fn load_from_sqlite() -> Result<LotteryState, String> {
+    let conn = open_db()?;
+
+    // 奖品
+    let mut stmt = conn
+        .prepare("SELECT id, color, name, description, icon, weight FROM prizes")
+        .map_err(|e| format!("查询奖品失败: {}", e))?;
+    let available_prizes = stmt
+        .query_map([], |row| {
+            let color: String = row.get(1)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                color,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<u32>>(5)?,
+            ))
+        })
+        .map_err(|e| format!("读取奖品失败: {}", e))?
+        .map(|r| r.map_err(|e| format!("读取奖品失败: {}", e)))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|(id, color, name, description, icon, weight)| {
+            Ok(Prize {
+                id,
+                color: color_from_db(&color)?,
+                name,
+                description,
+                icon,
+                weight,
+            })
+        })
+        .collect::<Result<Vec<Prize>, String>>()?;
+    drop(stmt);
+
+    // 配置
+    let config = conn
+        .query_row(
+            "SELECT draws_per_cycle, draws_per_color, enable_animations, animation_duration,
+                auto_backup, storage_backend FROM config WHERE id = 1",
+            [],
+            |row| {
+                Ok(LotteryConfig {
+                    draws_per_cycle: row.get(0)?,
+                    draws_per_color: row.get(1)?,
+                    enable_animations: row.get::<_, i64>(2)? != 0,
+                    animation_duration: row.get(3)?,
+                    auto_backup: row.get::<_, i64>(4)? != 0,
+                    storage_backend: match row.get::<_, String>(5)?.as_str() {
+                        "sqlite" => StorageBackend::Sqlite,
+                        _ => StorageBackend::Json,
+                    },
+                })
+            },
+        )
+        .map_err(|e| format!("读取配置失败: {}", e))?;
+
+    // 当前周期与历史周期
+    let current_id: String = conn
+        .query_row(
+            "SELECT id FROM cycles WHERE is_current = 1 LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("读取当前周期失败: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT id FROM cycles WHERE is_current = 0 ORDER BY start_time")
+        .map_err(|e| format!("查询历史周期失败: {}", e))?;
+    let history_ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("读取历史周期失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取历史周期失败: {}", e))?;
+    drop(stmt);
+
+    let current_cycle = load_cycle(&conn, &current_id)?;
+    let mut history = Vec::with_capacity(history_ids.len());
+    for id in history_ids {
+        history.push(load_cycle(&conn, &id)?);
+    }
+
+    log::info!("抽奖数据已从 SQLite 加载");
+    Ok(LotteryState {
+        current_cycle,
+        history,
+        available_prizes,
+        config,
+    })
+}
+
+/**
+ * 一次性将现有 `data.json` 迁移到 SQLite
+ *
+ * 读取当前路径的 JSON，在一个事务内写入全部历史周期与当前周期，核对行数与
+ * `validate_lottery_state` 一致后，将旧 JSON 重命名为 `data.migrated.json`。
+ */
+#[tauri::command]
+pub async fn migrate_to_sqlite(
+    manager: tauri::State<'_, StorageManager>,
+) -> Result<(), String> {
+    let _guard = manager.lock.lock().await;
+
+    let data_path = get_data_path()?;
+    if !data_path.exists() {
+        return Err("没有找到 data.json，无法迁移".to_string());
+    }
+
+    let json_data = tokio_fs::read_to_string(&data_path)
+        .await
+        .map_err(|e| format!("读取文件失败: {}", e))?;
+    let state: LotteryState = serde_json::from_str(&json_data)
+        .map_err(|e| format!("数据格式错误，可能已损坏: {}", e))?;
+
+    if !validate_lottery_state(&state)? {
+        return Err("数据未通过完整性校验，已中止迁移".to_string());
+    }
+
+    // 把后端切换持久化进配置，使迁移后 load/save 一致地指向 SQLite
+    let mut state = state;
+    state.config.storage_backend = StorageBackend::Sqlite;
+
+    save_to_sqlite(&state)?;
+
+    // 核对写入的结果行数与源数据一致
+    let expected_results: usize = state.current_cycle.results.len()
+        + state.history.iter().map(|c| c.results.len()).sum::<usize>();
+    let conn = open_db()?;
+    let actual_results: usize = conn
+        .query_row("SELECT COUNT(*) FROM results", [], |row| row.get::<_, i64>(0))
+        .map_err(|e| format!("统计结果行数失败: {}", e))? as usize;
+    if actual_results != expected_results {
+        return Err(format!(
+            "迁移行数不一致：期望 {}，实际 {}",
+            expected_results, actual_results
+        ));
+    }
+
+    let migrated_path = data_path.with_file_name("data.migrated.json");
+    tokio_fs::rename(&data_path, &migrated_path)
+        .await
+        .map_err(|e| format!("重命名旧数据文件失败: {}", e))?;
+
+    log::info!("已迁移到 SQLite，旧数据保留在: {:?}", migrated_path);
+    Ok(())
+}
+
+/**
+ * 单个颜色的统计
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColorStat {
+    pub color: PrizeColor,
+    pub count: u64,
+    #[serde(rename = "observedFrequency")]
+    pub observed_frequency: f64,
+}
+
+/**
+ * 单个奖品的统计，含观测频率与按配置权重得到的期望频率之间的偏差
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrizeStat {
+    #[serde(rename = "prizeId")]
+    pub prize_id: String,
+    pub color: PrizeColor,
+    pub count: u64,
+    #[serde(rename = "observedFrequency")]
+    pub observed_frequency: f64,
+    #[serde(rename = "configuredWeight")]
+    pub configured_weight: u32,
+    #[serde(rename = "expectedFrequency")]
+    pub expected_frequency: f64,
+    pub deviation: f64,
+}
+
+/**
+ * 抽奖统计与审计报告
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotteryStats {
+    #[serde(rename = "totalDraws")]
+    pub total_draws: u64,
+    #[serde(rename = "colorStats")]
+    pub color_stats: Vec<ColorStat>,
+    #[serde(rename = "prizeStats")]
+    pub prize_stats: Vec<PrizeStat>,
+    #[serde(rename = "completedCycles")]
+    pub completed_cycles: u64,
+    #[serde(rename = "abandonedCycles")]
+    pub abandoned_cycles: u64,
+    #[serde(rename = "averageCycleDurationMs")]
+    pub average_cycle_duration_ms: Option<f64>,
+    /// 结果条数与 `draws_per_cycle` 不符的周期 id，用于暴露篡改或损坏
+    #[serde(rename = "inconsistentCycleIds")]
+    pub inconsistent_cycle_ids: Vec<String>,
+}
+
+/**
+ * 汇总当前周期与全部历史周期，生成可审计的抽奖统计
+ *
+ * 统计每种颜色、每个奖品的中奖次数与观测频率，已完成与被放弃的周期数，
+ * 由 `start_time`/`end_time` 得到的平均周期时长，以及每个奖品观测频率相对
+ * 配置权重的偏差；并标记结果条数与 `draws_per_cycle` 不符的周期。
+ */
+#[tauri::command]
+pub async fn lottery_statistics(state: LotteryState) -> Result<LotteryStats, String> {
+    // 奖品 id -> (颜色, 权重)
+    let prize_meta: HashMap<&str, (&PrizeColor, u32)> = state
+        .available_prizes
+        .iter()
+        .map(|p| (p.id.as_str(), (&p.color, prize_weight(p))))
+        .collect();
+
+    // 各颜色配置总权重
+    let mut color_weight_total: HashMap<&'static str, u32> = HashMap::new();
+    for p in &state.available_prizes {
+        *color_weight_total.entry(color_to_db(&p.color)).or_insert(0) += prize_weight(p);
+    }
+
+    let cycles: Vec<&LotteryCycle> = std::iter::once(&state.current_cycle)
+        .chain(state.history.iter())
+        .collect();
+
+    let mut prize_counts: HashMap<String, u64> = HashMap::new();
+    let mut color_counts: HashMap<&'static str, u64> = HashMap::new();
+    let mut total_draws: u64 = 0;
+
+    let mut completed_cycles: u64 = 0;
+    let mut abandoned_cycles: u64 = 0;
+    let mut durations: Vec<i64> = Vec::new();
+    let mut inconsistent_cycle_ids: Vec<String> = Vec::new();
+
+    for (idx, cycle) in cycles.iter().enumerate() {
+        let is_current = idx == 0;
+
+        for r in &cycle.results {
+            total_draws += 1;
+            *prize_counts.entry(r.prize_id.clone()).or_insert(0) += 1;
+            // 颜色以奖品池为唯一权威来源，不做 id 字面猜测
+            if let Some((color, _)) = prize_meta.get(r.prize_id.as_str()) {
+                *color_counts.entry(color_to_db(color)).or_insert(0) += 1;
+            }
+        }
+
+        if cycle.completed {
+            completed_cycles += 1;
+        } else if !is_current {
+            // 未完成且已沉入历史，视为被放弃
+            abandoned_cycles += 1;
+        }
+
+        if let Some(end) = cycle.end_time {
+            durations.push(end - cycle.start_time);
+        }
+
+        let results_len = cycle.results.len() as u32;
+        if results_len > state.config.draws_per_cycle
+            || (cycle.completed && results_len != state.config.draws_per_cycle)
+        {
+            inconsistent_cycle_ids.push(cycle.id.clone());
+        }
+    }
+
+    let total_f = total_draws as f64;
+
+    let color_stats = [PrizeColor::Red, PrizeColor::Yellow, PrizeColor::Blue]
+        .into_iter()
+        .map(|color| {
+            let count = *color_counts.get(color_to_db(&color)).unwrap_or(&0);
+            ColorStat {
+                observed_frequency: if total_draws == 0 {
+                    0.0
+                } else {
+                    count as f64 / total_f
+                },
+                count,
+                color,
+            }
+        })
+        .collect();
+
+    let mut prize_stats: Vec<PrizeStat> = state
+        .available_prizes
+        .iter()
+        .map(|p| {
+            let count = *prize_counts.get(&p.id).unwrap_or(&0);
+            let observed = if total_draws == 0 {
+                0.0
+            } else {
+                count as f64 / total_f
+            };
+            let weight = prize_weight(p);
+            let color_key = color_to_db(&p.color);
+            let color_count = *color_counts.get(color_key).unwrap_or(&0);
+            let color_weight = *color_weight_total.get(color_key).unwrap_or(&0);
+            // 期望频率 = 该颜色观测占比 × 奖品在本色池内的权重占比
+            let expected = if total_draws == 0 || color_weight == 0 {
+                0.0
+            } else {
+                (color_count as f64 / total_f) * (weight as f64 / color_weight as f64)
+            };
+            PrizeStat {
+                prize_id: p.id.clone(),
+                color: p.color.clone(),
+                count,
+                observed_frequency: observed,
+                configured_weight: weight,
+                expected_frequency: expected,
+                deviation: observed - expected,
+            }
+        })
+        .collect();
+    prize_stats.sort_by(|a, b| b.count.cmp(&a.count));
+
+    let average_cycle_duration_ms = if durations.is_empty() {
+        None
+    } else {
+        Some(durations.iter().sum::<i64>() as f64 / durations.len() as f64)
+    };
+
+    Ok(LotteryStats {
+        total_draws,
+        color_stats,
+        prize_stats,
+        completed_cycles,
+        abandoned_cycles,
+        average_cycle_duration_ms,
+        inconsistent_cycle_ids,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prize(id: &str, color: PrizeColor, weight: Option<u32>) -> Prize {
+        Prize {
+            id: id.to_string(),
+            color,
+            name: id.to_string(),
+            description: None,
+            icon: None,
+            weight,
+        }
+    }
+
+    fn sample_prizes() -> Vec<Prize> {
+        vec![
+            prize("prize_red_1", PrizeColor::Red, Some(3)),
+            prize("prize_red_2", PrizeColor::Red, Some(1)),
+            prize("prize_yellow_1", PrizeColor::Yellow, None),
+            prize("prize_yellow_2", PrizeColor::Yellow, None),
+            prize("prize_blue_1", PrizeColor::Blue, None),
+            prize("prize_blue_2", PrizeColor::Blue, None),
+        ]
+    }
+
+    fn sample_state() -> LotteryState {
+        let mut state = create_default_state();
+        state.available_prizes = sample_prizes();
+        state.config.auto_backup = false;
+        state
+    }
+
+    /// 抽满当前周期，返回已完成的状态（种子在整个周期内不变，显式传入）
+    fn draw_until_complete() -> LotteryState {
+        let mut state = sample_state();
+        let seed = state.current_cycle.server_seed.clone().unwrap();
+        loop {
+            let (_, next) = perform_draw(state.clone(), &seed).unwrap();
+            state = next;
+            if state.current_cycle.completed {
+                break;
+            }
+        }
+        state
+    }
+
+    #[test]
+    fn select_weighted_respects_boundaries() {
+        let prizes = sample_prizes();
+        let pool: Vec<&Prize> = prizes.iter().filter(|p| p.color == PrizeColor::Red).collect();
+        // 累积权重 [3, 4]：r∈[0,3) 命中下标 0，r=3 命中下标 1
+        assert_eq!(select_weighted(&pool, 0), Some(0));
+        assert_eq!(select_weighted(&pool, 2), Some(0));
+        assert_eq!(select_weighted(&pool, 3), Some(1));
+        // rand 先按总权重取模：7 % 4 = 3 → 下标 1
+        assert_eq!(select_weighted(&pool, 7), Some(1));
+        // 空池无可选
+        assert_eq!(select_weighted(&[], 0), None);
+    }
+
+    #[test]
+    fn select_weighted_favors_higher_weight() {
+        let prizes = sample_prizes();
+        let pool: Vec<&Prize> = prizes.iter().filter(|p| p.color == PrizeColor::Red).collect();
+        let mut counts = [0u64; 2];
+        for r in 0..1000u64 {
+            counts[select_weighted(&pool, r).unwrap()] += 1;
+        }
+        // 权重 3:1，下标 0 应明显多于下标 1
+        assert!(counts[0] > counts[1]);
+    }
+
+    #[tokio::test]
+    async fn draw_then_verify_round_trip() {
+        let state = draw_until_complete();
+        let ok = verify_cycle(state.current_cycle.clone(), state.available_prizes.clone())
+            .await
+            .unwrap();
+        assert!(ok);
+    }
+
+    #[tokio::test]
+    async fn verify_rejects_swapped_same_color_prize() {
+        let state = draw_until_complete();
+        let mut cycle = state.current_cycle.clone();
+        // 把某条红色记录换成同色的另一个奖品：颜色仍能对上，但池内加权选择对不上
+        for r in cycle.results.iter_mut() {
+            if r.prize_id == "prize_red_1" {
+                r.prize_id = "prize_red_2".to_string();
+                break;
+            } else if r.prize_id == "prize_red_2" {
+                r.prize_id = "prize_red_1".to_string();
+                break;
+            }
+        }
+        let ok = verify_cycle(cycle, state.available_prizes.clone())
+            .await
+            .unwrap();
+        assert!(!ok);
+    }
+
+    #[tokio::test]
+    async fn statistics_counts_add_up() {
+        let state = draw_until_complete();
+        let stats = lottery_statistics(state.clone()).await.unwrap();
+
+        let expected = state.current_cycle.results.len() as u64;
+        assert_eq!(stats.total_draws, expected);
+        assert_eq!(
+            stats.color_stats.iter().map(|c| c.count).sum::<u64>(),
+            expected
+        );
+        assert_eq!(
+            stats.prize_stats.iter().map(|p| p.count).sum::<u64>(),
+            expected
+        );
+    }
+}